@@ -0,0 +1,176 @@
+//! Credential-signing broker.
+//!
+//! This listens on its own IPC endpoint rather than being exposed as a
+//! `#[tauri::command]`. Tauri commands can only ever be invoked by the
+//! webview/renderer — the embedded Node server is a separate OS process and
+//! has no way to call one, so gating a command on "caller is the spawned
+//! server" is unenforceable there. A listener on a dedicated endpoint can
+//! authenticate the actual connecting peer via the transport's own
+//! credentials (`SO_PEERCRED` / `GetNamedPipeClientProcessId`), so only the
+//! server process we spawned can ever reach the signing logic.
+use crate::{profiles, ServerState};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+#[derive(Debug, Deserialize)]
+struct SignRequest {
+    service: String,
+    headers: std::collections::HashMap<String, String>,
+    body: Option<String>,
+    /// Name of the header to inject the resolved secret into, e.g. "Authorization"
+    auth_header: String,
+    /// Optional prefix placed before the secret value, e.g. "Bearer "
+    auth_prefix: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SignedRequest {
+    headers: std::collections::HashMap<String, String>,
+    body: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BrokerError {
+    error: String,
+}
+
+const SIGN_REQUEST_KEY: &str = "api_key";
+
+/// Spawn a task that listens on `endpoint` for signing requests. Every
+/// connection is checked against the PID currently recorded in
+/// `ServerState` (rather than a PID captured once), so it keeps
+/// authenticating correctly across supervisor restarts. Likewise, the
+/// profile to sign with is read from `ServerState.active_profile` per
+/// connection rather than captured once, so switching the active profile
+/// takes effect on the next signed request instead of being silently
+/// ignored for the lifetime of the broker.
+pub fn start(app: AppHandle, endpoint: String) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(run_listener(app, endpoint))
+}
+
+#[cfg(unix)]
+async fn run_listener(app: AppHandle, endpoint: String) {
+    let listener = match tokio::net::UnixListener::bind(&endpoint) {
+        Ok(listener) => listener,
+        Err(_) => return,
+    };
+
+    loop {
+        let (stream, _addr) = match listener.accept().await {
+            Ok(pair) => pair,
+            Err(_) => continue,
+        };
+
+        let peer_pid = stream.peer_cred().ok().and_then(|cred| cred.pid()).map(|pid| pid as u32);
+        if !is_expected(&app, peer_pid) {
+            continue;
+        }
+
+        tokio::spawn(handle_connection(stream, app.clone()));
+    }
+}
+
+#[cfg(windows)]
+async fn run_listener(app: AppHandle, endpoint: String) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    loop {
+        let server = match ServerOptions::new().create(&endpoint) {
+            Ok(server) => server,
+            Err(_) => return,
+        };
+
+        if server.connect().await.is_err() {
+            continue;
+        }
+
+        let peer_pid = named_pipe_client_pid(&server).ok();
+        if !is_expected(&app, peer_pid) {
+            continue;
+        }
+
+        tokio::spawn(handle_connection(server, app.clone()));
+    }
+}
+
+fn is_expected(app: &AppHandle, peer_pid: Option<u32>) -> bool {
+    let Some(peer_pid) = peer_pid else {
+        return false;
+    };
+    let state = app.state::<ServerState>();
+    let expected_pid = state.server_pid.lock().ok().and_then(|lock| *lock);
+    expected_pid == Some(peer_pid)
+}
+
+#[cfg(windows)]
+fn named_pipe_client_pid(
+    server: &tokio::net::windows::named_pipe::NamedPipeServer,
+) -> std::io::Result<u32> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Pipes::GetNamedPipeClientProcessId;
+
+    let handle = server.as_raw_handle() as isize;
+    let mut pid: u32 = 0;
+    let ok = unsafe { GetNamedPipeClientProcessId(handle, &mut pid) };
+    if ok == 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(pid)
+    }
+}
+
+async fn handle_connection<S>(mut stream: S, app: AppHandle)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut line = String::new();
+    {
+        let mut reader = BufReader::new(&mut stream);
+        if reader.read_line(&mut line).await.is_err() {
+            return;
+        }
+    }
+
+    let active_profile = {
+        let state = app.state::<ServerState>();
+        match state.active_profile.lock() {
+            Ok(lock) => lock.clone(),
+            Err(_) => return,
+        }
+    };
+
+    let response = match serde_json::from_str::<SignRequest>(line.trim()) {
+        Ok(mut request) => match sign(&active_profile, &mut request) {
+            Ok(signed) => serde_json::to_string(&signed),
+            Err(e) => serde_json::to_string(&BrokerError { error: e }),
+        },
+        Err(e) => serde_json::to_string(&BrokerError {
+            error: format!("Invalid sign request: {}", e),
+        }),
+    };
+
+    if let Ok(mut line) = response {
+        line.push('\n');
+        let _ = stream.write_all(line.as_bytes()).await;
+    }
+}
+
+fn sign(profile: &str, request: &mut SignRequest) -> Result<SignedRequest, String> {
+    let entry_name = profiles::entry_name(profile, &request.service, SIGN_REQUEST_KEY);
+    let entry = keyring::Entry::new(&entry_name, "worldmirror")
+        .map_err(|e| format!("Failed to open keychain entry: {}", e))?;
+    let secret = entry
+        .get_password()
+        .map_err(|e| format!("Failed to read keychain entry: {}", e))?;
+
+    let prefix = request.auth_prefix.take().unwrap_or_default();
+    request
+        .headers
+        .insert(request.auth_header.clone(), format!("{}{}", prefix, secret));
+
+    Ok(SignedRequest {
+        headers: std::mem::take(&mut request.headers),
+        body: request.body.take(),
+    })
+}
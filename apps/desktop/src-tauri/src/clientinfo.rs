@@ -0,0 +1,27 @@
+use sysinfo::System;
+
+/// Information about a local process identified via an IPC connection.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Client {
+    pub pid: u32,
+    pub exe: Option<String>,
+    pub name: String,
+}
+
+/// Resolve a process ID to its executable path and name.
+///
+/// The PID itself comes from the transport layer (a Unix socket's
+/// `SO_PEERCRED` or a named pipe's client process id via
+/// `GetNamedPipeClientProcessId`), which is what actually proves which local
+/// process is on the other end of the connection.
+pub fn resolve_client(pid: u32) -> Option<Client> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let process = system.process(sysinfo::Pid::from_u32(pid))?;
+    Some(Client {
+        pid,
+        exe: process.exe().map(|p| p.to_string_lossy().into_owned()),
+        name: process.name().to_string_lossy().into_owned(),
+    })
+}
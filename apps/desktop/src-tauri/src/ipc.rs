@@ -0,0 +1,102 @@
+//! IPC transport used to talk to the embedded server without opening a loopback
+//! TCP port. Uses a Windows named pipe or a Unix domain socket, depending on
+//! platform, scoped to the current user by OS-level ACLs.
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// Health check response read back over the IPC endpoint
+#[derive(Debug, Deserialize)]
+struct HealthResponse {
+    ok: bool,
+}
+
+/// Generate a fresh, unique IPC endpoint for this server instance.
+#[cfg(unix)]
+pub fn generate_endpoint() -> String {
+    let dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/worldmirror-{}.sock", dir, random_suffix())
+}
+
+/// Generate a fresh, unique IPC endpoint for this server instance.
+#[cfg(windows)]
+pub fn generate_endpoint() -> String {
+    format!(r"\\.\pipe\worldmirror-{}", random_suffix())
+}
+
+fn random_suffix() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{:x}-{:x}", std::process::id(), nanos)
+}
+
+#[cfg(unix)]
+async fn connect(endpoint: &str) -> std::io::Result<(tokio::net::UnixStream, Option<u32>)> {
+    let stream = tokio::net::UnixStream::connect(endpoint).await?;
+    let peer_pid = stream.peer_cred().ok().and_then(|cred| cred.pid()).map(|pid| pid as u32);
+    Ok((stream, peer_pid))
+}
+
+#[cfg(windows)]
+async fn connect(
+    endpoint: &str,
+) -> std::io::Result<(tokio::net::windows::named_pipe::NamedPipeClient, Option<u32>)> {
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(endpoint)?;
+    let peer_pid = named_pipe_server_pid(&stream).ok();
+    Ok((stream, peer_pid))
+}
+
+#[cfg(windows)]
+fn named_pipe_server_pid(
+    stream: &tokio::net::windows::named_pipe::NamedPipeClient,
+) -> std::io::Result<u32> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Pipes::GetNamedPipeServerProcessId;
+
+    let handle = stream.as_raw_handle() as isize;
+    let mut pid: u32 = 0;
+    let ok = unsafe { GetNamedPipeServerProcessId(handle, &mut pid) };
+    if ok == 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(pid)
+    }
+}
+
+/// Connect to the endpoint and read back the verified PID of whatever process
+/// is listening on the other end, without performing a full health exchange.
+pub async fn current_peer_pid(endpoint: &str) -> Result<u32, String> {
+    let (_stream, peer_pid) = connect(endpoint)
+        .await
+        .map_err(|e| format!("Failed to connect to IPC endpoint: {}", e))?;
+    peer_pid.ok_or_else(|| "Could not verify peer PID for IPC endpoint".to_string())
+}
+
+/// Wait for the server to become healthy over the IPC endpoint. Returns the
+/// verified PID of the process that answered once it does, so callers can
+/// confirm it's the process they spawned.
+pub async fn wait_for_server(endpoint: &str, max_retries: u32) -> Result<u32, String> {
+    for attempt in 0..max_retries {
+        if let Ok((mut stream, peer_pid)) = connect(endpoint).await {
+            if stream.write_all(b"health\n").await.is_ok() {
+                let mut reader = BufReader::new(&mut stream);
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.is_ok() {
+                    if let Ok(health) = serde_json::from_str::<HealthResponse>(line.trim()) {
+                        if health.ok {
+                            return peer_pid
+                                .ok_or_else(|| "Could not verify peer PID for IPC endpoint".to_string());
+                        }
+                    }
+                }
+            }
+        }
+        // Exponential backoff: 200ms, 400ms, 800ms, ...
+        let delay = std::time::Duration::from_millis(200 * (1 << attempt.min(4)));
+        tokio::time::sleep(delay).await;
+    }
+
+    Err(format!("Server did not become healthy after {} attempts", max_retries))
+}
@@ -1,18 +1,22 @@
-use serde::{Deserialize, Serialize};
-use std::process::Command as StdCommand;
+mod broker;
+mod clientinfo;
+mod ipc;
+mod profiles;
+mod supervisor;
+
+use clientinfo::Client;
+use serde::Serialize;
 use std::sync::Mutex;
 use tauri::{Manager, State};
 
 /// Holds the server process state
-struct ServerState {
-    port: Mutex<Option<u16>>,
-    server_pid: Mutex<Option<u32>>,
-}
-
-/// Health check response from the local server
-#[derive(Debug, Deserialize)]
-struct HealthResponse {
-    ok: bool,
+pub(crate) struct ServerState {
+    endpoint: Mutex<Option<String>>,
+    pub(crate) server_pid: Mutex<Option<u32>>,
+    pub(crate) active_profile: Mutex<String>,
+    pub(crate) child: Mutex<Option<tokio::process::Child>>,
+    supervisor: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    broker: Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
 /// Keychain operation result
@@ -23,87 +27,164 @@ struct KeychainResult {
     error: Option<String>,
 }
 
-/// Find an available port for the local server
-fn find_available_port() -> u16 {
-    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind to port");
-    listener.local_addr().expect("Failed to get local addr").port()
-}
-
-/// Wait for the server to become healthy
-async fn wait_for_server(port: u16, max_retries: u32) -> Result<(), String> {
-    let client = reqwest::Client::new();
-    let url = format!("http://127.0.0.1:{}/health", port);
-
-    for attempt in 0..max_retries {
-        match client.get(&url).send().await {
-            Ok(resp) => {
-                if resp.status().is_success() {
-                    if let Ok(health) = resp.json::<HealthResponse>().await {
-                            if health.ok {
-                                return Ok(());
-                            }
-                        }
-                }
-            }
-            Err(_) => {}
-        }
-        // Exponential backoff: 200ms, 400ms, 800ms, ...
-        let delay = std::time::Duration::from_millis(200 * (1 << attempt.min(4)));
-        tokio::time::sleep(delay).await;
-    }
-
-    Err(format!("Server did not become healthy after {} attempts", max_retries))
-}
-
 /// Tauri command: Start the embedded server
 #[tauri::command]
-async fn start_server(state: State<'_, ServerState>) -> Result<u16, String> {
-    let mut port_lock = state.port.lock().map_err(|e| e.to_string())?;
-    if let Some(port) = *port_lock {
-        return Ok(port);
+async fn start_server(app: tauri::AppHandle, state: State<'_, ServerState>) -> Result<String, String> {
+    if let Some(endpoint) = state.endpoint.lock().map_err(|e| e.to_string())?.clone() {
+        return Ok(endpoint);
     }
 
-    let port = find_available_port();
+    let endpoint = ipc::generate_endpoint();
+    let broker_endpoint = ipc::generate_endpoint();
+    let active_profile = state.active_profile.lock().map_err(|e| e.to_string())?.clone();
 
-    // Spawn the Node.js server as a child process
-    let child = StdCommand::new("node")
-        .args(["--import", "tsx", "../server/src/main.ts"])
-        .env("PORT", port.to_string())
-        .env("HOST", "127.0.0.1")
-        .spawn()
-        .map_err(|e| format!("Failed to spawn server: {}", e))?;
+    let child = supervisor::spawn_child(&endpoint, &broker_endpoint, &active_profile)?;
+    let pid = child.id().ok_or_else(|| "Spawned server has no PID".to_string())?;
 
-    let pid = child.id();
-
-    // Store the PID for cleanup
+    // Store the child handle and PID for supervision and cleanup
+    {
+        let mut child_lock = state.child.lock().map_err(|e| e.to_string())?;
+        *child_lock = Some(child);
+    }
     {
         let mut pid_lock = state.server_pid.lock().map_err(|e| e.to_string())?;
         *pid_lock = Some(pid);
     }
 
-    // Wait for the server to become healthy
-    wait_for_server(port, 20).await?;
+    // Start the signing broker before waiting on the handshake below — the
+    // server has the broker endpoint as soon as it's spawned and may try to
+    // use it immediately on boot, so nothing must be listening after it.
+    // Authenticated via the IPC transport's own peer credentials rather than
+    // a webview-facing command.
+    let broker_handle = broker::start(app.clone(), broker_endpoint.clone());
+    {
+        let mut broker_lock = state.broker.lock().map_err(|e| e.to_string())?;
+        *broker_lock = Some(broker_handle);
+    }
+
+    // Wait for the server to become healthy, and confirm it's the process we spawned
+    let peer_pid = ipc::wait_for_server(&endpoint, 20).await?;
+    if peer_pid != pid {
+        return Err("IPC endpoint answered but peer PID did not match the spawned server".to_string());
+    }
 
-    *port_lock = Some(port);
-    Ok(port)
+    {
+        let mut endpoint_lock = state.endpoint.lock().map_err(|e| e.to_string())?;
+        *endpoint_lock = Some(endpoint.clone());
+    }
+
+    // Start supervising the server so a crash gets noticed and recovered from
+    let supervisor_handle = supervisor::start(app, endpoint.clone(), broker_endpoint);
+    {
+        let mut supervisor_lock = state.supervisor.lock().map_err(|e| e.to_string())?;
+        *supervisor_lock = Some(supervisor_handle);
+    }
+
+    Ok(endpoint)
+}
+
+/// Tauri command: Get the server's IPC endpoint (pipe or socket path)
+#[tauri::command]
+fn get_server_endpoint(state: State<'_, ServerState>) -> Option<String> {
+    state.endpoint.lock().ok().and_then(|lock| lock.clone())
+}
+
+/// Tauri command: Resolve the identity of the process currently backing the
+/// server's IPC endpoint.
+///
+/// A Tauri command can only ever be invoked by the webview, never by the
+/// spawned Node process directly, so this can't be used to identify an
+/// arbitrary "connected client" — connecting to the endpoint ourselves just
+/// tells us who the server is. It confirms our recorded server is still the
+/// one actually holding the endpoint (catching a hijacked or stale socket),
+/// not who is calling this command.
+#[tauri::command]
+async fn get_connected_client(state: State<'_, ServerState>) -> Result<Option<Client>, String> {
+    let endpoint = state
+        .endpoint
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone()
+        .ok_or_else(|| "Server is not running".to_string())?;
+
+    let pid = ipc::current_peer_pid(&endpoint).await?;
+    Ok(clientinfo::resolve_client(pid))
+}
+
+/// Tauri command: List all stored credential profile names
+#[tauri::command]
+fn keychain_list_profiles() -> Result<Vec<String>, String> {
+    profiles::list_profiles()
 }
 
-/// Tauri command: Get the server port
+/// Tauri command: Create a new credential profile
 #[tauri::command]
-fn get_server_port(state: State<'_, ServerState>) -> Option<u16> {
-    state.port.lock().ok().and_then(|lock| *lock)
+fn keychain_create_profile(name: String) -> Result<(), String> {
+    profiles::create_profile(&name)
 }
 
-/// Tauri command: Store API key in OS keychain
+/// Tauri command: Delete a credential profile and every key stored under it
 #[tauri::command]
-fn keychain_set(service: String, key: String, value: String) -> KeychainResult {
-    let entry_name = format!("worldmirror.{}.{}", service, key);
+fn keychain_delete_profile(name: String, state: State<'_, ServerState>) -> Result<(), String> {
+    profiles::delete_profile(&name)?;
+
+    // The deleted profile can no longer be selected, so fall back to the
+    // default rather than leaving `active_profile` pointing at a profile
+    // that's now invisible to keychain_list_profiles but still live.
+    let mut active = state.active_profile.lock().map_err(|e| e.to_string())?;
+    if *active == name {
+        *active = profiles::DEFAULT_PROFILE.to_string();
+    }
+    Ok(())
+}
+
+/// Tauri command: Set which profile is active for subsequent keychain operations
+#[tauri::command]
+fn set_active_profile(name: String, state: State<'_, ServerState>) -> Result<(), String> {
+    let mut active = state.active_profile.lock().map_err(|e| e.to_string())?;
+    *active = name;
+    Ok(())
+}
+
+/// Tauri command: Get the currently active profile
+#[tauri::command]
+fn get_active_profile(state: State<'_, ServerState>) -> Result<String, String> {
+    Ok(state.active_profile.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// Tauri command: Store API key in OS keychain, under the active profile
+#[tauri::command]
+fn keychain_set(
+    service: String,
+    key: String,
+    value: String,
+    state: State<'_, ServerState>,
+) -> KeychainResult {
+    let active_profile = match state.active_profile.lock() {
+        Ok(lock) => lock.clone(),
+        Err(e) => {
+            return KeychainResult {
+                success: false,
+                value: None,
+                error: Some(format!("Failed to read active profile: {}", e)),
+            }
+        }
+    };
+
+    let entry_name = profiles::entry_name(&active_profile, &service, &key);
     match keyring::Entry::new(&entry_name, "worldmirror") {
         Ok(entry) => match entry.set_password(&value) {
-            Ok(()) => KeychainResult {
-                success: true,
-                value: None,
-                error: None,
+            Ok(()) => match profiles::record_entry(&active_profile, &service, &key) {
+                Ok(()) => KeychainResult {
+                    success: true,
+                    value: None,
+                    error: None,
+                },
+                Err(e) => KeychainResult {
+                    success: false,
+                    value: None,
+                    error: Some(format!("Failed to record keychain entry: {}", e)),
+                },
             },
             Err(e) => KeychainResult {
                 success: false,
@@ -119,10 +200,27 @@ fn keychain_set(service: String, key: String, value: String) -> KeychainResult {
     }
 }
 
-/// Tauri command: Retrieve API key from OS keychain
+/// Tauri command: Retrieve API key from OS keychain, from the active profile
+///
+/// This is renderer-facing (e.g. the settings UI displaying a stored key) —
+/// a Tauri command can never be invoked by the spawned Node server, so there
+/// is no caller identity to gate here. Operations that must be restricted to
+/// the server, like request signing, go through the broker's IPC listener
+/// instead, which can actually authenticate the connecting peer.
 #[tauri::command]
-fn keychain_get(service: String, key: String) -> KeychainResult {
-    let entry_name = format!("worldmirror.{}.{}", service, key);
+fn keychain_get(service: String, key: String, state: State<'_, ServerState>) -> KeychainResult {
+    let active_profile = match state.active_profile.lock() {
+        Ok(lock) => lock.clone(),
+        Err(e) => {
+            return KeychainResult {
+                success: false,
+                value: None,
+                error: Some(format!("Failed to read active profile: {}", e)),
+            }
+        }
+    };
+
+    let entry_name = profiles::entry_name(&active_profile, &service, &key);
     match keyring::Entry::new(&entry_name, "worldmirror") {
         Ok(entry) => match entry.get_password() {
             Ok(password) => KeychainResult {
@@ -149,22 +247,37 @@ fn keychain_get(service: String, key: String) -> KeychainResult {
     }
 }
 
-/// Tauri command: Delete API key from OS keychain
+/// Tauri command: Delete API key from OS keychain, from the active profile
 #[tauri::command]
-fn keychain_delete(service: String, key: String) -> KeychainResult {
-    let entry_name = format!("worldmirror.{}.{}", service, key);
+fn keychain_delete(service: String, key: String, state: State<'_, ServerState>) -> KeychainResult {
+    let active_profile = match state.active_profile.lock() {
+        Ok(lock) => lock.clone(),
+        Err(e) => {
+            return KeychainResult {
+                success: false,
+                value: None,
+                error: Some(format!("Failed to read active profile: {}", e)),
+            }
+        }
+    };
+
+    let entry_name = profiles::entry_name(&active_profile, &service, &key);
     match keyring::Entry::new(&entry_name, "worldmirror") {
         Ok(entry) => match entry.delete_credential() {
-            Ok(()) => KeychainResult {
-                success: true,
-                value: None,
-                error: None,
-            },
-            Err(keyring::Error::NoEntry) => KeychainResult {
-                success: true,
-                value: None,
-                error: None,
-            },
+            Ok(()) | Err(keyring::Error::NoEntry) => {
+                match profiles::forget_entry(&active_profile, &service, &key) {
+                    Ok(()) => KeychainResult {
+                        success: true,
+                        value: None,
+                        error: None,
+                    },
+                    Err(e) => KeychainResult {
+                        success: false,
+                        value: None,
+                        error: Some(format!("Failed to update entry registry: {}", e)),
+                    },
+                }
+            }
             Err(e) => KeychainResult {
                 success: false,
                 value: None,
@@ -185,12 +298,22 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_os::init())
         .manage(ServerState {
-            port: Mutex::new(None),
+            endpoint: Mutex::new(None),
             server_pid: Mutex::new(None),
+            active_profile: Mutex::new(profiles::DEFAULT_PROFILE.to_string()),
+            child: Mutex::new(None),
+            supervisor: Mutex::new(None),
+            broker: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             start_server,
-            get_server_port,
+            get_server_endpoint,
+            get_connected_client,
+            keychain_list_profiles,
+            keychain_create_profile,
+            keychain_delete_profile,
+            set_active_profile,
+            get_active_profile,
             keychain_set,
             keychain_get,
             keychain_delete,
@@ -199,6 +322,19 @@ pub fn run() {
             if let tauri::WindowEvent::Destroyed = event {
                 // Cleanup: kill the server process on window close
                 if let Some(state) = window.try_state::<ServerState>() {
+                    // Cancel the supervisor and broker first so neither
+                    // resurrects or keeps serving the server while we're
+                    // shutting it down
+                    if let Ok(mut supervisor_lock) = state.supervisor.lock() {
+                        if let Some(handle) = supervisor_lock.take() {
+                            handle.abort();
+                        }
+                    }
+                    if let Ok(mut broker_lock) = state.broker.lock() {
+                        if let Some(handle) = broker_lock.take() {
+                            handle.abort();
+                        }
+                    }
                     if let Ok(pid_lock) = state.server_pid.lock() {
                         if let Some(pid) = *pid_lock {
                             #[cfg(unix)]
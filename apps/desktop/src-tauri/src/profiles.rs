@@ -0,0 +1,130 @@
+//! Multi-profile credential storage.
+//!
+//! `keyring::Entry` has no way to enumerate what's stored, so profile names
+//! and the set of keys stored under each profile are tracked in dedicated
+//! registry entries alongside the actual secrets.
+use keyring::Entry;
+
+const REGISTRY_SERVICE: &str = "worldmirror.__registry__";
+pub const DEFAULT_PROFILE: &str = "default";
+
+fn registry_entry(name: &str) -> Result<Entry, String> {
+    Entry::new(REGISTRY_SERVICE, name).map_err(|e| format!("Failed to open profile registry: {}", e))
+}
+
+/// The keychain entry name for a given profile/service/key triple
+pub fn entry_name(profile: &str, service: &str, key: &str) -> String {
+    format!("worldmirror.{}.{}.{}", profile, service, key)
+}
+
+/// List all known profile names. The `default` profile always exists, even
+/// before anything has been written to the registry.
+pub fn list_profiles() -> Result<Vec<String>, String> {
+    let entry = registry_entry("profiles")?;
+    match entry.get_password() {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        Err(keyring::Error::NoEntry) => Ok(vec![DEFAULT_PROFILE.to_string()]),
+        Err(e) => Err(format!("Failed to read profile registry: {}", e)),
+    }
+}
+
+fn save_profiles(profiles: &[String]) -> Result<(), String> {
+    let entry = registry_entry("profiles")?;
+    let json = serde_json::to_string(profiles).map_err(|e| e.to_string())?;
+    entry
+        .set_password(&json)
+        .map_err(|e| format!("Failed to write profile registry: {}", e))
+}
+
+/// Create a new profile. No-op if it already exists.
+pub fn create_profile(name: &str) -> Result<(), String> {
+    let mut profiles = list_profiles()?;
+    if !profiles.iter().any(|p| p == name) {
+        profiles.push(name.to_string());
+        save_profiles(&profiles)?;
+    }
+    Ok(())
+}
+
+/// Delete a profile and cascade-delete every keychain entry stored under it.
+pub fn delete_profile(name: &str) -> Result<(), String> {
+    if name == DEFAULT_PROFILE {
+        return Err("The default profile cannot be deleted".to_string());
+    }
+
+    for key in list_entries(name)? {
+        if let Some((service, key)) = key.split_once('\u{1}') {
+            if let Ok(entry) = Entry::new(&entry_name(name, service, key), "worldmirror") {
+                match entry.delete_credential() {
+                    Ok(()) | Err(keyring::Error::NoEntry) => {}
+                    Err(e) => return Err(format!("Failed to delete keychain entry: {}", e)),
+                }
+            }
+        }
+    }
+    clear_entries(name)?;
+
+    let mut profiles = list_profiles()?;
+    profiles.retain(|p| p != name);
+    save_profiles(&profiles)
+}
+
+fn entries_registry_name(profile: &str) -> String {
+    format!("entries.{}", profile)
+}
+
+fn list_entries(profile: &str) -> Result<Vec<String>, String> {
+    let entry = registry_entry(&entries_registry_name(profile))?;
+    match entry.get_password() {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        Err(keyring::Error::NoEntry) => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read entry registry for profile: {}", e)),
+    }
+}
+
+fn clear_entries(profile: &str) -> Result<(), String> {
+    let entry = registry_entry(&entries_registry_name(profile))?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to clear entry registry for profile: {}", e)),
+    }
+}
+
+/// Record that `service`/`key` has been written under `profile`, so it can be
+/// cascade-deleted later. Service and key are joined with a `\u{1}` control
+/// character and split back apart at its first occurrence; names that
+/// themselves contain that character are not rejected and split wrongly.
+pub fn record_entry(profile: &str, service: &str, key: &str) -> Result<(), String> {
+    let mut entries = list_entries(profile)?;
+    let combined = format!("{}\u{1}{}", service, key);
+    if !entries.iter().any(|e| e == &combined) {
+        entries.push(combined);
+        let entry = registry_entry(&entries_registry_name(profile))?;
+        let json = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+        entry
+            .set_password(&json)
+            .map_err(|e| format!("Failed to write entry registry for profile: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Remove the record that `service`/`key` was written under `profile`. Call
+/// this whenever a single credential is deleted outside of a full
+/// `delete_profile` cascade, so it isn't left listed in the registry forever.
+pub fn forget_entry(profile: &str, service: &str, key: &str) -> Result<(), String> {
+    let mut entries = list_entries(profile)?;
+    let combined = format!("{}\u{1}{}", service, key);
+    let before = entries.len();
+    entries.retain(|e| e != &combined);
+    if entries.len() == before {
+        return Ok(());
+    }
+    if entries.is_empty() {
+        return clear_entries(profile);
+    }
+    let entry = registry_entry(&entries_registry_name(profile))?;
+    let json = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    entry
+        .set_password(&json)
+        .map_err(|e| format!("Failed to write entry registry for profile: {}", e))
+}
@@ -0,0 +1,124 @@
+//! Watches the embedded server and restarts it if it crashes, so the UI
+//! doesn't silently lose its backend until the window is closed.
+use crate::{ipc, ServerState};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+
+/// Spawn the Node.js server as a child process talking over `endpoint`, with
+/// `broker_endpoint` for signed-request brokering and `profile` as the
+/// active credential profile.
+pub fn spawn_child(
+    endpoint: &str,
+    broker_endpoint: &str,
+    profile: &str,
+) -> Result<tokio::process::Child, String> {
+    tokio::process::Command::new("node")
+        .args(["--import", "tsx", "../server/src/main.ts"])
+        .env("WORLDMIRROR_IPC_ENDPOINT", endpoint)
+        .env("WORLDMIRROR_BROKER_ENDPOINT", broker_endpoint)
+        .env("WORLDMIRROR_ACTIVE_PROFILE", profile)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn server: {}", e))
+}
+
+/// Spawn a background task that periodically probes server health and
+/// watches the child handle for exit, respawning on crash with the same
+/// endpoint. Restarts are capped and back off exponentially to avoid a crash
+/// loop. The caller is responsible for aborting the returned handle before
+/// tearing down the server.
+///
+/// The profile to respawn with is read from `ServerState.active_profile` at
+/// respawn time rather than captured once, so a profile switch between
+/// restarts is picked up instead of resurrecting the server under a stale
+/// profile.
+pub fn start(
+    app: AppHandle,
+    endpoint: String,
+    broker_endpoint: String,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut restart_count = 0u32;
+
+        loop {
+            tokio::time::sleep(HEALTH_POLL_INTERVAL).await;
+
+            let state = app.state::<ServerState>();
+
+            let exited = {
+                let mut child_lock = match state.child.lock() {
+                    Ok(lock) => lock,
+                    Err(_) => return,
+                };
+                match child_lock.as_mut() {
+                    Some(child) => matches!(child.try_wait(), Ok(Some(_))),
+                    None => true,
+                }
+            };
+
+            let unhealthy = exited || ipc::current_peer_pid(&endpoint).await.is_err();
+            if !unhealthy {
+                continue;
+            }
+
+            let _ = app.emit("server-unhealthy", ());
+
+            if restart_count >= MAX_RESTART_ATTEMPTS {
+                break;
+            }
+
+            let backoff = Duration::from_millis(500 * (1 << restart_count.min(5)));
+            tokio::time::sleep(backoff).await;
+
+            let profile = match state.active_profile.lock() {
+                Ok(lock) => lock.clone(),
+                Err(_) => return,
+            };
+
+            restart_count += 1;
+            if respawn(&state, &endpoint, &broker_endpoint, &profile).await.is_ok() {
+                let _ = app.emit("server-restarted", ());
+            }
+        }
+    })
+}
+
+async fn respawn(
+    state: &tauri::State<'_, ServerState>,
+    endpoint: &str,
+    broker_endpoint: &str,
+    profile: &str,
+) -> Result<(), String> {
+    // The old process may still be alive (e.g. wedged but failing its health
+    // probe) and holding `endpoint`; kill it before binding a replacement, or
+    // the new child will fail to bind the stale path and wait_for_server will
+    // just time out below.
+    let old_child = {
+        let mut child_lock = state.child.lock().map_err(|e| e.to_string())?;
+        child_lock.take()
+    };
+    if let Some(mut old) = old_child {
+        let _ = old.kill().await;
+    }
+
+    let child = spawn_child(endpoint, broker_endpoint, profile)?;
+    let pid = child.id().ok_or_else(|| "Respawned server has no PID".to_string())?;
+
+    {
+        let mut child_lock = state.child.lock().map_err(|e| e.to_string())?;
+        *child_lock = Some(child);
+    }
+    {
+        let mut pid_lock = state.server_pid.lock().map_err(|e| e.to_string())?;
+        *pid_lock = Some(pid);
+    }
+
+    let peer_pid = ipc::wait_for_server(endpoint, 20).await?;
+    if peer_pid != pid {
+        return Err("IPC endpoint answered but peer PID did not match the respawned server".to_string());
+    }
+
+    Ok(())
+}